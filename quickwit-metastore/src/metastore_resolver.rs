@@ -23,6 +23,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::postgres::{PostgresMetastoreFactory, PostgresqlAliasMetastoreFactory};
 use crate::{Metastore, MetastoreResolverError};
 
 /// A metastore factory builds a [`Metastore`] object from an URI.
@@ -46,9 +47,12 @@ impl Default for MetastoreUriResolver {
         //     per_protocol_resolver: Default::default(),
         // };
         // resolver.register(SingleFileMetastoreFactory::default());
-        MetastoreUriResolver {
+        let mut resolver = MetastoreUriResolver {
             per_protocol_resolver: Default::default(),
-        }
+        };
+        resolver.register(PostgresMetastoreFactory::default());
+        resolver.register(PostgresqlAliasMetastoreFactory::default());
+        resolver
     }
 }
 
@@ -0,0 +1,85 @@
+/*
+    Quickwit
+    Copyright (C) 2021 Quickwit Inc.
+
+    Quickwit is offered under the AGPL v3.0 and as commercial software.
+    For commercial licensing, contact us at hello@quickwit.io.
+
+    AGPL:
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::sync::Arc;
+
+use tokio::runtime::Handle;
+
+use super::pool::build_pool;
+use super::store::PostgresMetastore;
+use crate::metastore_resolver::MetastoreFactory;
+use crate::{Metastore, MetastoreResult};
+
+/// Creates a [`PostgresMetastore`] for the `postgres://` and `postgresql://`
+/// protocols.
+///
+/// Unlike the single-file metastore, each call to [`resolve`](Self::resolve)
+/// for a given URI shares one pooled set of connections (see
+/// [`build_pool`]) rather than taking an exclusive lock on a single JSON
+/// file, so multiple indexers and searchers can observe a consistent view
+/// of the metastore concurrently.
+///
+/// `resolve` blocks the calling thread on the async connect-and-migrate
+/// step via [`tokio::task::block_in_place`], which requires a
+/// multi-threaded Tokio runtime: it panics if called from a current-thread
+/// runtime (the single-file/S3 factories have no such requirement, so this
+/// only matters for `postgres://`/`postgresql://` URIs).
+#[derive(Default)]
+pub struct PostgresMetastoreFactory;
+
+impl MetastoreFactory for PostgresMetastoreFactory {
+    fn protocol(&self) -> String {
+        "postgres".to_string()
+    }
+
+    fn resolve(&self, uri: &str) -> MetastoreResult<Arc<dyn Metastore>> {
+        let pool = build_pool(uri)?;
+        // Block on the async schema migration: `resolve` is a synchronous
+        // entry point shared with the other factories, but connecting to
+        // Postgres and running the `indexes`/`splits` migrations both
+        // require an async client. Requires a multi-threaded runtime; see
+        // the struct-level doc comment above.
+        let metastore = tokio::task::block_in_place(|| {
+            Handle::current().block_on(PostgresMetastore::connect_and_migrate(pool))
+        })?;
+        Ok(Arc::new(metastore))
+    }
+}
+
+/// The second factory instance registered for the `postgresql://` alias.
+///
+/// [`MetastoreUriResolver`](crate::metastore_resolver::MetastoreUriResolver)
+/// keys factories by a single protocol string, so the `postgresql` spelling
+/// gets its own thin factory that defers to the same
+/// [`PostgresMetastore::connect_and_migrate`] logic.
+#[derive(Default)]
+pub struct PostgresqlAliasMetastoreFactory;
+
+impl MetastoreFactory for PostgresqlAliasMetastoreFactory {
+    fn protocol(&self) -> String {
+        "postgresql".to_string()
+    }
+
+    fn resolve(&self, uri: &str) -> MetastoreResult<Arc<dyn Metastore>> {
+        PostgresMetastoreFactory.resolve(uri)
+    }
+}
@@ -0,0 +1,37 @@
+/*
+    Quickwit
+    Copyright (C) 2021 Quickwit Inc.
+
+    Quickwit is offered under the AGPL v3.0 and as commercial software.
+    For commercial licensing, contact us at hello@quickwit.io.
+
+    AGPL:
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Relational metastore backend.
+//!
+//! Stores index metadata, sources, splits, and checkpoints in PostgreSQL
+//! tables rather than a single JSON file, and hands out connections from a
+//! pool shared by every caller resolving the same URI. See
+//! [`factory::PostgresMetastoreFactory`] for the `postgres://` /
+//! `postgresql://` entry points.
+
+mod factory;
+mod model;
+mod pool;
+mod store;
+
+pub use factory::{PostgresMetastoreFactory, PostgresqlAliasMetastoreFactory};
+pub use store::PostgresMetastore;
@@ -0,0 +1,173 @@
+/*
+    Quickwit
+    Copyright (C) 2021 Quickwit Inc.
+
+    Quickwit is offered under the AGPL v3.0 and as commercial software.
+    For commercial licensing, contact us at hello@quickwit.io.
+
+    AGPL:
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+/// Schema applied on first connect by [`super::store::PostgresMetastore::connect_and_migrate`].
+///
+/// `indexes` stores one row per `IndexUid`, with the full `IndexMetadata`
+/// kept as JSON for forward-compatibility (new config fields land without a
+/// migration), while `splits` gets real columns for the fields the
+/// metastore filters and joins on: the state machine column in particular
+/// drives `list_splits`'s most common queries (staged vs. published vs.
+/// marked-for-deletion).
+pub(crate) const MIGRATIONS: &str = r#"
+CREATE TABLE IF NOT EXISTS indexes (
+    index_uid           TEXT PRIMARY KEY,
+    index_id             TEXT NOT NULL,
+    incarnation_id        TEXT NOT NULL,
+    index_metadata_json  JSONB NOT NULL,
+    checkpoint_json       JSONB NOT NULL DEFAULT '{}',
+    created_at             TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (index_id)
+);
+
+CREATE TABLE IF NOT EXISTS sources (
+    index_uid    TEXT NOT NULL REFERENCES indexes (index_uid) ON DELETE CASCADE,
+    source_id     TEXT NOT NULL,
+    source_config_json TEXT NOT NULL,
+    PRIMARY KEY (index_uid, source_id)
+);
+
+CREATE TABLE IF NOT EXISTS splits (
+    split_id      TEXT PRIMARY KEY,
+    index_uid     TEXT NOT NULL REFERENCES indexes (index_uid) ON DELETE CASCADE,
+    split_state   TEXT NOT NULL DEFAULT 'staged',
+    split_metadata_json TEXT NOT NULL,
+    time_range_start BIGINT,
+    time_range_end   BIGINT,
+    create_timestamp BIGINT NOT NULL,
+    update_timestamp BIGINT NOT NULL,
+    publish_timestamp BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS splits_index_uid_state_idx ON splits (index_uid, split_state);
+"#;
+
+/// `split_state` counterpart of [`crate::SplitState`]. The column itself is
+/// plain `TEXT` rather than a Postgres enum: `tokio_postgres` binds Rust
+/// `&str` parameters as `TEXT`/`VARCHAR`, with no way to target a custom
+/// enum OID, so comparing or writing `split_state` with a bound parameter
+/// would otherwise fail at runtime with a param/column type mismatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SplitStateColumn {
+    Staged,
+    Published,
+    MarkedForDeletion,
+}
+
+impl SplitStateColumn {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SplitStateColumn::Staged => "staged",
+            SplitStateColumn::Published => "published",
+            SplitStateColumn::MarkedForDeletion => "marked_for_deletion",
+        }
+    }
+
+    pub fn from_sql(value: &str) -> Self {
+        match value {
+            "published" => SplitStateColumn::Published,
+            "marked_for_deletion" => SplitStateColumn::MarkedForDeletion,
+            _ => SplitStateColumn::Staged,
+        }
+    }
+}
+
+impl From<crate::SplitState> for SplitStateColumn {
+    fn from(state: crate::SplitState) -> Self {
+        match state {
+            crate::SplitState::Staged => SplitStateColumn::Staged,
+            crate::SplitState::Published => SplitStateColumn::Published,
+            crate::SplitState::MarkedForDeletion => SplitStateColumn::MarkedForDeletion,
+        }
+    }
+}
+
+impl From<SplitStateColumn> for crate::SplitState {
+    fn from(column: SplitStateColumn) -> Self {
+        match column {
+            SplitStateColumn::Staged => crate::SplitState::Staged,
+            SplitStateColumn::Published => crate::SplitState::Published,
+            SplitStateColumn::MarkedForDeletion => crate::SplitState::MarkedForDeletion,
+        }
+    }
+}
+
+/// Row shape returned by the `indexes` lookups, kept separate from
+/// [`crate::IndexMetadata`] so a schema change here does not ripple through
+/// every metastore backend.
+#[derive(Debug)]
+pub(crate) struct IndexRow {
+    pub index_uid: String,
+    pub index_metadata_json: serde_json::Value,
+}
+
+impl IndexRow {
+    pub fn from_tokio_row(row: &tokio_postgres::Row) -> Self {
+        IndexRow {
+            index_uid: row.get("index_uid"),
+            index_metadata_json: row.get("index_metadata_json"),
+        }
+    }
+}
+
+/// Row shape returned by `list_splits` queries.
+#[derive(Debug)]
+pub(crate) struct SplitRow {
+    pub split_id: String,
+    pub index_uid: String,
+    pub split_state: SplitStateColumn,
+    pub split_metadata_json: String,
+    pub update_timestamp: i64,
+    pub publish_timestamp: Option<i64>,
+}
+
+impl SplitRow {
+    pub fn from_tokio_row(row: &tokio_postgres::Row) -> Self {
+        let split_state: String = row.get("split_state");
+        SplitRow {
+            split_id: row.get("split_id"),
+            index_uid: row.get("index_uid"),
+            split_state: SplitStateColumn::from_sql(&split_state),
+            split_metadata_json: row.get("split_metadata_json"),
+            update_timestamp: row.get("update_timestamp"),
+            publish_timestamp: row.get("publish_timestamp"),
+        }
+    }
+}
+
+/// Row shape returned by the source lookups of
+/// `index_metadata`/`list_indexes_metadatas`.
+#[derive(Debug)]
+pub(crate) struct SourceRow {
+    pub source_id: String,
+    pub source_config_json: String,
+}
+
+impl SourceRow {
+    pub fn from_tokio_row(row: &tokio_postgres::Row) -> Self {
+        SourceRow {
+            source_id: row.get("source_id"),
+            source_config_json: row.get("source_config_json"),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+/*
+    Quickwit
+    Copyright (C) 2021 Quickwit Inc.
+
+    Quickwit is offered under the AGPL v3.0 and as commercial software.
+    For commercial licensing, contact us at hello@quickwit.io.
+
+    AGPL:
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use once_cell::sync::Lazy;
+use tokio_postgres::NoTls;
+
+use crate::MetastoreError;
+
+/// URI-keyed cache of pools, so that every call to
+/// [`super::PostgresMetastoreFactory::resolve`] for the same URI shares one
+/// fixed-size pool instead of each opening its own. `Pool` clones cheaply
+/// (it's an `Arc` around the shared inner pool state), so callers get an
+/// independent handle onto the same set of connections.
+static POOL_REGISTRY: Lazy<Mutex<HashMap<String, Pool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Default number of connections kept in the pool when the URI does not
+/// override it with `?max_connections=`.
+const DEFAULT_MAX_CONNECTIONS: usize = 10;
+
+/// Default connect timeout applied to each pooled connection when the URI
+/// does not override it with `?connect_timeout_secs=`.
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// Connection parameters extracted from a `postgres://` or `postgresql://`
+/// metastore URI's query string.
+#[derive(Debug, Clone)]
+pub(crate) struct PostgresPoolParams {
+    pub max_connections: usize,
+    pub connect_timeout: Duration,
+}
+
+impl PostgresPoolParams {
+    /// Parses pool-related query params (`max_connections`,
+    /// `connect_timeout_secs`) out of a metastore URI, falling back to
+    /// sane defaults when absent or malformed.
+    pub fn from_uri(uri: &str) -> Self {
+        let query = uri.split_once('?').map(|(_, query)| query).unwrap_or("");
+        let params: HashMap<String, String> = query
+            .split('&')
+            .filter_map(|kv| kv.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let max_connections = params
+            .get("max_connections")
+            .and_then(|value| usize::from_str(value).ok())
+            .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+        let connect_timeout_secs = params
+            .get("connect_timeout_secs")
+            .and_then(|value| u64::from_str(value).ok())
+            .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+
+        Self {
+            max_connections,
+            connect_timeout: Duration::from_secs(connect_timeout_secs),
+        }
+    }
+
+    /// Strips the query string from a metastore URI, leaving the bare DSN
+    /// that `tokio_postgres` expects.
+    pub fn strip_query(uri: &str) -> &str {
+        uri.split_once('?').map(|(dsn, _)| dsn).unwrap_or(uri)
+    }
+}
+
+/// Returns the fixed-size [`deadpool_postgres::Pool`] for the given URI,
+/// building and caching it in [`POOL_REGISTRY`] on first use.
+///
+/// The pool is created once per resolved URI and shared by every caller of
+/// [`super::PostgresMetastoreFactory::resolve`] for that URI, so that many
+/// indexers and searchers can hand out pooled connections instead of each
+/// holding an exclusive lock on a single metastore file.
+pub(crate) fn build_pool(uri: &str) -> Result<Pool, MetastoreError> {
+    let mut registry = POOL_REGISTRY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(pool) = registry.get(uri) {
+        return Ok(pool.clone());
+    }
+
+    let params = PostgresPoolParams::from_uri(uri);
+    let dsn = PostgresPoolParams::strip_query(uri);
+
+    let mut config = Config::new();
+    config.url = Some(dsn.to_string());
+    config.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    });
+    config.pool = Some(deadpool_postgres::PoolConfig {
+        max_size: params.max_connections,
+        timeouts: deadpool_postgres::Timeouts {
+            wait: Some(params.connect_timeout),
+            create: Some(params.connect_timeout),
+            recycle: Some(params.connect_timeout),
+        },
+        ..Default::default()
+    });
+
+    let pool = config
+        .create_pool(Some(Runtime::Tokio1), NoTls)
+        .map_err(|error| MetastoreError::ConnectionError {
+            message: error.to_string(),
+        })?;
+    registry.insert(uri.to_string(), pool.clone());
+    Ok(pool)
+}
@@ -0,0 +1,495 @@
+/*
+    Quickwit
+    Copyright (C) 2021 Quickwit Inc.
+
+    Quickwit is offered under the AGPL v3.0 and as commercial software.
+    For commercial licensing, contact us at hello@quickwit.io.
+
+    AGPL:
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as
+    published by the Free Software Foundation, either version 3 of the
+    License, or (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+use async_trait::async_trait;
+use deadpool_postgres::{Pool, Transaction};
+
+use super::model::{IndexRow, SourceRow, SplitRow, SplitStateColumn, MIGRATIONS};
+use crate::checkpoint::IndexCheckpointDelta;
+use crate::{
+    IndexMetadata, IndexUid, Metastore, MetastoreError, MetastoreResult, SourceConfig, Split,
+    SplitMetadata, SplitState,
+};
+
+/// Metastore backend that stores index metadata, sources, splits, and
+/// checkpoints as rows in a PostgreSQL database instead of a single JSON
+/// file.
+///
+/// Connections are handed out from a [`Pool`] built once by
+/// [`super::factory::PostgresMetastoreFactory::resolve`], so concurrent
+/// indexers and searchers observe a consistent view of the metastore
+/// without contending on a file lock.
+pub struct PostgresMetastore {
+    pool: Pool,
+}
+
+impl PostgresMetastore {
+    /// Connects to the pool's target database and applies [`MIGRATIONS`] if
+    /// the `indexes`/`sources`/`splits` tables do not already exist.
+    pub(crate) async fn connect_and_migrate(pool: Pool) -> MetastoreResult<Self> {
+        let client =
+            pool.get()
+                .await
+                .map_err(|error| MetastoreError::ConnectionError {
+                    message: error.to_string(),
+                })?;
+        client
+            .batch_execute(MIGRATIONS)
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(PostgresMetastore { pool })
+    }
+
+    async fn client(&self) -> MetastoreResult<deadpool_postgres::Client> {
+        self.pool
+            .get()
+            .await
+            .map_err(|error| MetastoreError::ConnectionError {
+                message: error.to_string(),
+            })
+    }
+
+    /// Loads the `sources` rows for `index_uid` and installs them onto
+    /// `index_metadata`, which is the source of truth for reads: `sources`
+    /// can be mutated independently of `indexes.index_metadata_json` via
+    /// `add_source`/`delete_source`, so a read always re-joins them rather
+    /// than trusting whatever sources map was last serialized into the
+    /// index metadata blob.
+    async fn hydrate_sources(
+        &self,
+        client: &deadpool_postgres::Client,
+        index_uid: &IndexUid,
+        index_metadata: &mut IndexMetadata,
+    ) -> MetastoreResult<()> {
+        let rows = client
+            .query(
+                "SELECT source_id, source_config_json FROM sources WHERE index_uid = $1",
+                &[&index_uid.to_string()],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        index_metadata.sources.clear();
+        for row in &rows {
+            let source_row = SourceRow::from_tokio_row(row);
+            let source_config: SourceConfig = serde_json::from_str(&source_row.source_config_json)
+                .map_err(|error| MetastoreError::JsonDeserializeError {
+                    struct_name: "SourceConfig".to_string(),
+                    message: error.to_string(),
+                })?;
+            index_metadata
+                .sources
+                .insert(source_row.source_id, source_config);
+        }
+        Ok(())
+    }
+
+    /// Applies `delta` to the index's checkpoint: loads the current
+    /// `index_metadata_json`, applies the delta to its in-memory
+    /// `checkpoint`, then writes both the updated metadata blob and the
+    /// denormalized `checkpoint_json` column back in the same transaction
+    /// that published the splits the delta accompanies.
+    async fn apply_checkpoint_delta(
+        &self,
+        transaction: &Transaction<'_>,
+        index_uid: &IndexUid,
+        delta: IndexCheckpointDelta,
+    ) -> MetastoreResult<()> {
+        let row = transaction
+            .query_one(
+                "SELECT index_metadata_json FROM indexes WHERE index_uid = $1 FOR UPDATE",
+                &[&index_uid.to_string()],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        let index_row = IndexRow::from_tokio_row(&row);
+        let mut index_metadata = deserialize_index_metadata(&index_row)?;
+
+        index_metadata
+            .checkpoint
+            .try_apply_delta(delta)
+            .map_err(|error| {
+                MetastoreError::Db(format!("checkpoint delta could not be applied: {error}"))
+            })?;
+
+        let index_metadata_json = serde_json::to_value(&index_metadata).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "IndexMetadata".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let checkpoint_json = serde_json::to_value(&index_metadata.checkpoint).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "IndexCheckpoint".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        transaction
+            .execute(
+                "UPDATE indexes SET index_metadata_json = $1, checkpoint_json = $2, \
+                 updated_at = now() WHERE index_uid = $3",
+                &[&index_metadata_json, &checkpoint_json, &index_uid.to_string()],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Metastore for PostgresMetastore {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.client().await?;
+        Ok(())
+    }
+
+    async fn create_index(&self, index_metadata: IndexMetadata) -> MetastoreResult<()> {
+        let mut client = self.client().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        let index_uid = index_metadata.index_uid.clone();
+        let index_id = index_metadata.index_id().to_string();
+        let index_metadata_json = serde_json::to_value(&index_metadata).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "IndexMetadata".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        transaction
+            .execute(
+                "INSERT INTO indexes (index_uid, index_id, incarnation_id, index_metadata_json) \
+                 VALUES ($1, $2, $3, $4)",
+                &[
+                    &index_uid.to_string(),
+                    &index_id,
+                    &index_uid.incarnation_id().to_string(),
+                    &index_metadata_json,
+                ],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        // Seed the relational `sources` table from whatever sources the
+        // caller already attached to the `IndexMetadata` (e.g. the index's
+        // configured sources at creation time), so future reads can treat
+        // the table as the source of truth for the full set of sources.
+        for (source_id, source_config) in &index_metadata.sources {
+            let source_config_json = serde_json::to_string(source_config).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    struct_name: "SourceConfig".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+            transaction
+                .execute(
+                    "INSERT INTO sources (index_uid, source_id, source_config_json) \
+                     VALUES ($1, $2, $3)",
+                    &[&index_uid.to_string(), source_id, &source_config_json],
+                )
+                .await
+                .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn index_exists(&self, index_id: &str) -> MetastoreResult<bool> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt("SELECT 1 FROM indexes WHERE index_id = $1", &[&index_id])
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(row.is_some())
+    }
+
+    async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                "SELECT index_uid, index_metadata_json FROM indexes WHERE index_id = $1",
+                &[&index_id],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?
+            .ok_or_else(|| MetastoreError::IndexDoesNotExist {
+                index_id: index_id.to_string(),
+            })?;
+        let index_row = IndexRow::from_tokio_row(&row);
+        let mut index_metadata = deserialize_index_metadata(&index_row)?;
+        let index_uid = index_metadata.index_uid.clone();
+        self.hydrate_sources(&client, &index_uid, &mut index_metadata)
+            .await?;
+        Ok(index_metadata)
+    }
+
+    async fn list_indexes_metadatas(&self) -> MetastoreResult<Vec<IndexMetadata>> {
+        let client = self.client().await?;
+        let rows = client
+            .query("SELECT index_uid, index_metadata_json FROM indexes", &[])
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        let mut indexes_metadatas = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let mut index_metadata = deserialize_index_metadata(&IndexRow::from_tokio_row(row))?;
+            let index_uid = index_metadata.index_uid.clone();
+            self.hydrate_sources(&client, &index_uid, &mut index_metadata)
+                .await?;
+            indexes_metadatas.push(index_metadata);
+        }
+        Ok(indexes_metadatas)
+    }
+
+    async fn delete_index(&self, index_uid: IndexUid) -> MetastoreResult<()> {
+        let client = self.client().await?;
+        let deleted = client
+            .execute(
+                "DELETE FROM indexes WHERE index_uid = $1",
+                &[&index_uid.to_string()],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        if deleted == 0 {
+            return Err(MetastoreError::IndexDoesNotExist {
+                index_id: index_uid.index_id().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn add_source(&self, index_uid: IndexUid, source: SourceConfig) -> MetastoreResult<()> {
+        let client = self.client().await?;
+        let source_config_json = serde_json::to_string(&source).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "SourceConfig".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        client
+            .execute(
+                "INSERT INTO sources (index_uid, source_id, source_config_json) \
+                 VALUES ($1, $2, $3)",
+                &[&index_uid.to_string(), &source.source_id, &source_config_json],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn delete_source(&self, index_uid: IndexUid, source_id: &str) -> MetastoreResult<()> {
+        let client = self.client().await?;
+        let deleted = client
+            .execute(
+                "DELETE FROM sources WHERE index_uid = $1 AND source_id = $2",
+                &[&index_uid.to_string(), &source_id],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        if deleted == 0 {
+            return Err(MetastoreError::Db(format!(
+                "source `{source_id}` does not exist for index `{index_uid}`"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn stage_splits(
+        &self,
+        index_uid: IndexUid,
+        split_metadata_list: Vec<SplitMetadata>,
+    ) -> MetastoreResult<()> {
+        let mut client = self.client().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        for split_metadata in &split_metadata_list {
+            let split_metadata_json = serde_json::to_string(split_metadata).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    struct_name: "SplitMetadata".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+            transaction
+                .execute(
+                    "INSERT INTO splits (split_id, index_uid, split_state, \
+                     split_metadata_json, create_timestamp, update_timestamp) \
+                     VALUES ($1, $2, $3, $4, $5, $5) \
+                     ON CONFLICT (split_id) DO NOTHING",
+                    &[
+                        &split_metadata.split_id(),
+                        &index_uid.to_string(),
+                        &SplitStateColumn::from(SplitState::Staged).as_sql(),
+                        &split_metadata_json,
+                        &split_metadata.create_timestamp,
+                    ],
+                )
+                .await
+                .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn publish_splits<'a>(
+        &self,
+        index_uid: IndexUid,
+        staged_split_ids: &[&'a str],
+        replaced_split_ids: &[&'a str],
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> MetastoreResult<()> {
+        let mut client = self.client().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        transaction
+            .execute(
+                "UPDATE splits SET split_state = $1, update_timestamp = extract(epoch from now()), \
+                 publish_timestamp = extract(epoch from now()) \
+                 WHERE index_uid = $2 AND split_id = ANY($3)",
+                &[
+                    &SplitStateColumn::from(SplitState::Published).as_sql(),
+                    &index_uid.to_string(),
+                    &staged_split_ids,
+                ],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        if !replaced_split_ids.is_empty() {
+            transaction
+                .execute(
+                    "UPDATE splits SET split_state = $1, update_timestamp = extract(epoch from now()) \
+                     WHERE index_uid = $2 AND split_id = ANY($3)",
+                    &[
+                        &SplitStateColumn::from(SplitState::MarkedForDeletion).as_sql(),
+                        &index_uid.to_string(),
+                        &replaced_split_ids,
+                    ],
+                )
+                .await
+                .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        }
+        if let Some(checkpoint_delta) = checkpoint_delta_opt {
+            self.apply_checkpoint_delta(&transaction, &index_uid, checkpoint_delta)
+                .await?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn mark_splits_for_deletion(
+        &self,
+        index_uid: IndexUid,
+        split_ids: &[&str],
+    ) -> MetastoreResult<()> {
+        let client = self.client().await?;
+        client
+            .execute(
+                "UPDATE splits SET split_state = $1, update_timestamp = extract(epoch from now()) \
+                 WHERE index_uid = $2 AND split_id = ANY($3)",
+                &[
+                    &SplitStateColumn::from(SplitState::MarkedForDeletion).as_sql(),
+                    &index_uid.to_string(),
+                    &split_ids,
+                ],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn delete_splits(&self, index_uid: IndexUid, split_ids: &[&str]) -> MetastoreResult<()> {
+        let client = self.client().await?;
+        client
+            .execute(
+                "DELETE FROM splits WHERE index_uid = $1 AND split_id = ANY($2)",
+                &[&index_uid.to_string(), &split_ids],
+            )
+            .await
+            .map_err(|error| MetastoreError::Db(error.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_splits(
+        &self,
+        index_uid: IndexUid,
+        state: Option<SplitState>,
+    ) -> MetastoreResult<Vec<Split>> {
+        let client = self.client().await?;
+        let rows = if let Some(state) = state {
+            client
+                .query(
+                    "SELECT split_id, index_uid, split_state, \
+                     split_metadata_json, update_timestamp, publish_timestamp \
+                     FROM splits WHERE index_uid = $1 AND split_state = $2",
+                    &[&index_uid.to_string(), &SplitStateColumn::from(state).as_sql()],
+                )
+                .await
+        } else {
+            client
+                .query(
+                    "SELECT split_id, index_uid, split_state, \
+                     split_metadata_json, update_timestamp, publish_timestamp \
+                     FROM splits WHERE index_uid = $1",
+                    &[&index_uid.to_string()],
+                )
+                .await
+        }
+        .map_err(|error| MetastoreError::Db(error.to_string()))?;
+
+        rows.iter()
+            .map(|row| deserialize_split(&SplitRow::from_tokio_row(row)))
+            .collect()
+    }
+}
+
+fn deserialize_index_metadata(row: &IndexRow) -> MetastoreResult<IndexMetadata> {
+    serde_json::from_value(row.index_metadata_json.clone()).map_err(|error| {
+        MetastoreError::JsonDeserializeError {
+            struct_name: "IndexMetadata".to_string(),
+            message: error.to_string(),
+        }
+    })
+}
+
+fn deserialize_split(row: &SplitRow) -> MetastoreResult<Split> {
+    let split_metadata: SplitMetadata = serde_json::from_str(&row.split_metadata_json)
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            struct_name: "SplitMetadata".to_string(),
+            message: error.to_string(),
+        })?;
+    Ok(Split {
+        split_metadata,
+        split_state: row.split_state.into(),
+        update_timestamp: row.update_timestamp,
+        publish_timestamp: row.publish_timestamp,
+    })
+}
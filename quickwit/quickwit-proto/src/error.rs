@@ -0,0 +1,65 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Shared error-handling types implemented by every Quickwit service error
+//! (indexing, search, metastore, ...), so REST handlers and gRPC status
+//! conversions can be written generically instead of matching on each
+//! service's own error enum.
+
+use serde::Serialize;
+
+/// Coarse classification used to pick the REST response's HTTP status and,
+/// on the gRPC side, the nearest `tonic::Code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceErrorCode {
+    BadRequest,
+    NotFound,
+    Internal,
+    Unavailable,
+}
+
+/// Broad error category, independent of the HTTP status it happens to map
+/// to, so a client can branch on "is this my fault or the server's" without
+/// parsing `message` or knowing every `error_code` up front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorType {
+    InvalidRequest,
+    NotFound,
+    Internal,
+    Unavailable,
+}
+
+/// Implemented by every error type returned from a Quickwit service
+/// boundary. `error_code` is the stable identifier carried across the gRPC
+/// boundary in `tonic::Status` metadata (see
+/// `quickwit_proto::indexing::IndexingError`'s `From` impls) and returned
+/// as the REST error body's `code`, so a lossy `tonic::Code`/HTTP status
+/// round-trip never has to be the only way a client tells errors apart.
+pub trait ServiceError: std::error::Error {
+    /// HTTP-ish status classification for the REST response status line.
+    fn status_code(&self) -> ServiceErrorCode;
+
+    /// Stable, snake_case machine-readable identifier for this error, e.g.
+    /// `"missing_pipeline"`.
+    fn error_code(&self) -> &'static str;
+
+    /// Broad category for this error, independent of `error_code`.
+    fn error_type(&self) -> ErrorType;
+}
@@ -23,7 +23,7 @@ use anyhow::anyhow;
 use quickwit_actors::AskError;
 use thiserror;
 
-use crate::{IndexUid, ServiceError, ServiceErrorCode};
+use crate::{ErrorType, IndexUid, ServiceError, ServiceErrorCode};
 
 #[path = "../codegen/quickwit/quickwit.indexing.rs"]
 mod codegen;
@@ -62,54 +62,164 @@ pub enum IndexingError {
     Unavailable,
 }
 
+/// Metadata key under which [`ServiceError::error_code`] is stashed on the
+/// gRPC status, so the original stable code survives the round-trip instead
+/// of being inferred back from the (lossy) tonic status code alone.
+const ERROR_CODE_METADATA_KEY: &str = "x-quickwit-error-code";
+/// Metadata key carrying `index_id`, read back by `From<tonic::Status>` so
+/// that `MissingPipeline`/`PipelineAlreadyExists` don't degrade to empty
+/// strings across the wire.
+const INDEX_ID_METADATA_KEY: &str = "x-quickwit-index-id";
+/// Metadata key carrying `source_id`, same purpose as
+/// [`INDEX_ID_METADATA_KEY`].
+const SOURCE_ID_METADATA_KEY: &str = "x-quickwit-source-id";
+
+/// Attaches the stable error code and, when present, the `index_id`/
+/// `source_id` fields as ASCII metadata on a [`tonic::Status`].
+fn status_with_metadata(
+    mut status: tonic::Status,
+    error_code: &str,
+    index_id: Option<&str>,
+    source_id: Option<&str>,
+) -> tonic::Status {
+    let metadata = status.metadata_mut();
+    if let Ok(value) = error_code.parse() {
+        metadata.insert(ERROR_CODE_METADATA_KEY, value);
+    }
+    if let Some(index_id) = index_id {
+        if let Ok(value) = index_id.parse() {
+            metadata.insert(INDEX_ID_METADATA_KEY, value);
+        }
+    }
+    if let Some(source_id) = source_id {
+        if let Ok(value) = source_id.parse() {
+            metadata.insert(SOURCE_ID_METADATA_KEY, value);
+        }
+    }
+    status
+}
+
+/// Reads a metadata value previously attached by [`status_with_metadata`]
+/// back out of a [`tonic::Status`], defaulting to an empty string when
+/// absent (e.g. when talking to an older peer that predates this field).
+fn metadata_str(status: &tonic::Status, key: &str) -> String {
+    status
+        .metadata()
+        .get(key)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
 impl From<IndexingError> for tonic::Status {
     fn from(error: IndexingError) -> Self {
+        let error_code = error.error_code().to_string();
         match error {
             IndexingError::MissingPipeline {
-                index_id,
-                source_id,
-            } => tonic::Status::not_found(format!("Missing pipeline {index_id}/{source_id}")),
+                ref index_id,
+                ref source_id,
+            } => {
+                let status = tonic::Status::not_found(format!(
+                    "Missing pipeline {index_id}/{source_id}"
+                ));
+                status_with_metadata(status, &error_code, Some(index_id), Some(source_id))
+            }
             IndexingError::PipelineAlreadyExists {
-                index_id,
-                source_id,
+                ref index_id,
+                ref source_id,
                 pipeline_ord,
-            } => tonic::Status::already_exists(format!(
-                "Pipeline {index_id}/{source_id} {pipeline_ord} already exists "
-            )),
-            IndexingError::Io(error) => tonic::Status::internal(error.to_string()),
-            IndexingError::InvalidParams(error) => {
-                tonic::Status::invalid_argument(error.to_string())
+            } => {
+                let status = tonic::Status::already_exists(format!(
+                    "Pipeline {index_id}/{source_id} {pipeline_ord} already exists "
+                ));
+                status_with_metadata(status, &error_code, Some(index_id), Some(source_id))
+            }
+            IndexingError::Io(error) => {
+                status_with_metadata(tonic::Status::internal(error.to_string()), &error_code, None, None)
             }
-            IndexingError::SpawnPipelinesError { pipeline_ids } => {
-                tonic::Status::internal(format!("Error spawning pipelines {:?}", pipeline_ids))
+            IndexingError::InvalidParams(error) => status_with_metadata(
+                tonic::Status::invalid_argument(error.to_string()),
+                &error_code,
+                None,
+                None,
+            ),
+            IndexingError::SpawnPipelinesError { ref pipeline_ids } => status_with_metadata(
+                tonic::Status::internal(format!("Error spawning pipelines {:?}", pipeline_ids)),
+                &error_code,
+                None,
+                None,
+            ),
+            IndexingError::Internal(ref string) => {
+                status_with_metadata(tonic::Status::internal(string.clone()), &error_code, None, None)
             }
-            IndexingError::Internal(string) => tonic::Status::internal(string),
-            IndexingError::MetastoreError(string) => tonic::Status::internal(string),
-            IndexingError::StorageResolverError(string) => tonic::Status::internal(string),
-            IndexingError::Unavailable => {
-                tonic::Status::unavailable("Indexing service is unavailable.")
+            IndexingError::MetastoreError(ref string) => {
+                status_with_metadata(tonic::Status::internal(string.clone()), &error_code, None, None)
             }
+            IndexingError::StorageResolverError(ref string) => {
+                status_with_metadata(tonic::Status::internal(string.clone()), &error_code, None, None)
+            }
+            IndexingError::Unavailable => status_with_metadata(
+                tonic::Status::unavailable("Indexing service is unavailable."),
+                &error_code,
+                None,
+                None,
+            ),
         }
     }
 }
 
 impl From<tonic::Status> for IndexingError {
     fn from(status: tonic::Status) -> Self {
-        match status.code() {
-            tonic::Code::InvalidArgument => {
-                IndexingError::InvalidParams(anyhow!(status.message().to_string()))
-            }
-            tonic::Code::NotFound => IndexingError::MissingPipeline {
-                index_id: "".to_string(),
-                source_id: "".to_string(),
+        let error_code = metadata_str(&status, ERROR_CODE_METADATA_KEY);
+        let index_id = metadata_str(&status, INDEX_ID_METADATA_KEY);
+        let source_id = metadata_str(&status, SOURCE_ID_METADATA_KEY);
+        // The error code is authoritative when present: it survives the
+        // round-trip even for codes that don't map to a distinct
+        // `tonic::Code` (e.g. several `internal`-mapped variants).
+        match error_code.as_str() {
+            "missing_pipeline" => IndexingError::MissingPipeline {
+                index_id,
+                source_id,
             },
-            tonic::Code::AlreadyExists => IndexingError::PipelineAlreadyExists {
-                index_id: "".to_string(),
-                source_id: "".to_string(),
+            "pipeline_already_exists" => IndexingError::PipelineAlreadyExists {
+                index_id,
+                source_id,
                 pipeline_ord: 0,
             },
-            tonic::Code::Unavailable => IndexingError::Unavailable,
-            _ => IndexingError::InvalidParams(anyhow!(status.message().to_string())),
+            "spawn_pipelines_error" => IndexingError::SpawnPipelinesError { pipeline_ids: vec![] },
+            "unavailable" => IndexingError::Unavailable,
+            "invalid_params" => IndexingError::InvalidParams(anyhow!(status.message().to_string())),
+            // These all map to `tonic::Code::Internal` on the way out (see
+            // `From<IndexingError> for tonic::Status` above), so the code
+            // alone is what tells them apart on the way back; falling
+            // through to the generic `tonic::Code` match below would
+            // collapse every one of them into `InvalidParams`, turning an
+            // internal error into a client-error response.
+            "io_error" => IndexingError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                status.message().to_string(),
+            )),
+            "internal_error" => IndexingError::Internal(status.message().to_string()),
+            "metastore_error" => IndexingError::MetastoreError(status.message().to_string()),
+            "storage_resolver_error" => {
+                IndexingError::StorageResolverError(status.message().to_string())
+            }
+            _ => match status.code() {
+                tonic::Code::InvalidArgument => {
+                    IndexingError::InvalidParams(anyhow!(status.message().to_string()))
+                }
+                tonic::Code::NotFound => IndexingError::MissingPipeline {
+                    index_id,
+                    source_id,
+                },
+                tonic::Code::AlreadyExists => IndexingError::PipelineAlreadyExists {
+                    index_id,
+                    source_id,
+                    pipeline_ord: 0,
+                },
+                tonic::Code::Unavailable => IndexingError::Unavailable,
+                _ => IndexingError::InvalidParams(anyhow!(status.message().to_string())),
+            },
         }
     }
 }
@@ -128,6 +238,34 @@ impl ServiceError for IndexingError {
             Self::Unavailable => ServiceErrorCode::Unavailable,
         }
     }
+
+    fn error_code(&self) -> &'static str {
+        match self {
+            Self::MissingPipeline { .. } => "missing_pipeline",
+            Self::PipelineAlreadyExists { .. } => "pipeline_already_exists",
+            Self::InvalidParams(_) => "invalid_params",
+            Self::SpawnPipelinesError { .. } => "spawn_pipelines_error",
+            Self::Io(_) => "io_error",
+            Self::Internal(_) => "internal_error",
+            Self::MetastoreError(_) => "metastore_error",
+            Self::StorageResolverError(_) => "storage_resolver_error",
+            Self::Unavailable => "unavailable",
+        }
+    }
+
+    fn error_type(&self) -> ErrorType {
+        match self {
+            Self::MissingPipeline { .. } => ErrorType::NotFound,
+            Self::PipelineAlreadyExists { .. } => ErrorType::InvalidRequest,
+            Self::InvalidParams(_) => ErrorType::InvalidRequest,
+            Self::SpawnPipelinesError { .. } => ErrorType::Internal,
+            Self::Io(_) => ErrorType::Internal,
+            Self::Internal(_) => ErrorType::Internal,
+            Self::MetastoreError(_) => ErrorType::Internal,
+            Self::StorageResolverError(_) => ErrorType::Internal,
+            Self::Unavailable => ErrorType::Unavailable,
+        }
+    }
 }
 
 impl From<AskError<IndexingError>> for IndexingError {
@@ -0,0 +1,269 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Content-type-driven parsing for the `_bulk`/`_ingest` endpoints.
+//!
+//! The Elasticsearch-compatible bulk endpoints historically only accepted
+//! the ES action/meta NDJSON wire format. This module lets them also accept
+//! plain NDJSON (one raw document per line) and CSV, by normalizing both
+//! into the action/meta NDJSON format the rest of the ingest path already
+//! understands.
+
+use bytes::Bytes;
+use serde::Deserialize;
+
+/// The document format of an incoming bulk/ingest payload, selected by the
+/// request's `Content-Type` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BulkBodyFormat {
+    /// Elasticsearch action/meta NDJSON: alternating action line and
+    /// source line. Passed through unchanged.
+    ElasticBulk,
+    /// One raw JSON document per non-empty line, with no action/meta
+    /// lines.
+    NdJson,
+    /// A header row of field names followed by one row of values per
+    /// document.
+    Csv,
+}
+
+impl BulkBodyFormat {
+    /// Selects a format from the request's `Content-Type` header, falling
+    /// back to the historical `ElasticBulk` format when the header is
+    /// absent or unrecognized so existing clients keep working unchanged.
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        match content_type.map(|value| value.split(';').next().unwrap_or("").trim()) {
+            Some("text/csv") => BulkBodyFormat::Csv,
+            Some("application/x-ndjson") => BulkBodyFormat::NdJson,
+            _ => BulkBodyFormat::ElasticBulk,
+        }
+    }
+}
+
+/// Query params controlling how non-Elasticsearch bulk payloads are
+/// converted to documents. Parsed independently from the ingest options
+/// already extracted by the `_bulk` filters.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct BulkFormatQueryParams {
+    /// When `false`, every CSV cell is kept as a JSON string instead of
+    /// being inferred as a number or boolean. Defaults to `true`.
+    pub infer_types: Option<bool>,
+    /// Target index for CSV/NDJSON documents submitted to the index-less
+    /// `/_elastic/_bulk` endpoint, which has no `{index}` path segment to
+    /// carry one. Ignored (and unnecessary) for the ES bulk action/meta
+    /// format, and for the `/_elastic/{index}/_bulk` and
+    /// `/_elastic/{index}/_ingest` endpoints, which take the index from
+    /// the URL.
+    pub index: Option<String>,
+}
+
+impl BulkFormatQueryParams {
+    pub fn infer_types(&self) -> bool {
+        self.infer_types.unwrap_or(true)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum BulkBodyError {
+    #[error("invalid CSV payload: {0}")]
+    InvalidCsv(String),
+    #[error("invalid NDJSON payload: {0}")]
+    InvalidNdJson(String),
+}
+
+impl warp::reject::Reject for BulkBodyError {}
+
+/// Normalizes a bulk/ingest payload of the given `format` into the
+/// action/meta NDJSON wire format expected by the existing ingest path,
+/// targeting `index` for every document (the index name carried by the
+/// URL path, or by the `index` query param on the index-less `_bulk`
+/// endpoint, since raw NDJSON/CSV documents have no per-document action
+/// line to carry one).
+pub(crate) fn normalize_to_bulk_ndjson(
+    format: BulkBodyFormat,
+    body: &Bytes,
+    index: &str,
+    infer_types: bool,
+) -> Result<Bytes, BulkBodyError> {
+    match format {
+        BulkBodyFormat::ElasticBulk => Ok(body.clone()),
+        BulkBodyFormat::NdJson => ndjson_to_bulk_ndjson(body, index),
+        BulkBodyFormat::Csv => csv_to_bulk_ndjson(body, index, infer_types),
+    }
+}
+
+fn action_line(index: &str) -> String {
+    format!("{{\"index\":{{\"_index\":\"{index}\"}}}}")
+}
+
+fn ndjson_to_bulk_ndjson(body: &Bytes, index: &str) -> Result<Bytes, BulkBodyError> {
+    let text = std::str::from_utf8(body)
+        .map_err(|error| BulkBodyError::InvalidNdJson(error.to_string()))?;
+    let mut out = String::with_capacity(text.len() * 2);
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Validate that each line is a JSON document before wrapping it so
+        // malformed input is rejected up front instead of surfacing as an
+        // opaque downstream bulk-action parse error.
+        serde_json::from_str::<serde_json::Value>(line)
+            .map_err(|error| BulkBodyError::InvalidNdJson(error.to_string()))?;
+        out.push_str(&action_line(index));
+        out.push('\n');
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(Bytes::from(out))
+}
+
+fn csv_to_bulk_ndjson(body: &Bytes, index: &str, infer_types: bool) -> Result<Bytes, BulkBodyError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(body.as_ref());
+    let headers = reader
+        .headers()
+        .map_err(|error| BulkBodyError::InvalidCsv(error.to_string()))?
+        .clone();
+
+    let mut out = String::new();
+    for result in reader.records() {
+        let record = result.map_err(|error| BulkBodyError::InvalidCsv(error.to_string()))?;
+        let mut document = serde_json::Map::with_capacity(headers.len());
+        for (field, cell) in headers.iter().zip(record.iter()) {
+            if cell.is_empty() {
+                continue;
+            }
+            let value = if infer_types {
+                infer_csv_value(cell)
+            } else {
+                serde_json::Value::String(cell.to_string())
+            };
+            document.insert(field.to_string(), value);
+        }
+        out.push_str(&action_line(index));
+        out.push('\n');
+        out.push_str(&serde_json::Value::Object(document).to_string());
+        out.push('\n');
+    }
+    Ok(Bytes::from(out))
+}
+
+/// Infers a JSON scalar type for one CSV cell: booleans, integers, and
+/// floats are recognized, everything else stays a string.
+///
+/// Both numeric branches require the parsed value to format back to
+/// exactly `cell`, so identifiers that merely look numeric round-trip as
+/// strings instead of being silently corrupted:
+/// - a leading-zero cell like a ZIP code (`"01234"`) would parse as the
+///   integer `1234`, losing the zero;
+/// - a cell with more digits than `i64` can hold falls through to `f64`,
+///   which is lossy for large integers;
+/// - `"inf"`/`"nan"` parse as non-finite `f64`, which `serde_json` can't
+///   represent and silently serializes as `null`, dropping the field.
+fn infer_csv_value(cell: &str) -> serde_json::Value {
+    if let Ok(value) = cell.parse::<i64>() {
+        if value.to_string() == cell {
+            return serde_json::Value::from(value);
+        }
+    }
+    if let Ok(value) = cell.parse::<f64>() {
+        if value.is_finite() && value.to_string() == cell {
+            return serde_json::Value::from(value);
+        }
+    }
+    match cell {
+        "true" => serde_json::Value::Bool(true),
+        "false" => serde_json::Value::Bool(false),
+        _ => serde_json::Value::String(cell.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_content_type() {
+        assert_eq!(
+            BulkBodyFormat::from_content_type(Some("text/csv")),
+            BulkBodyFormat::Csv
+        );
+        assert_eq!(
+            BulkBodyFormat::from_content_type(Some("application/x-ndjson")),
+            BulkBodyFormat::NdJson
+        );
+        assert_eq!(
+            BulkBodyFormat::from_content_type(Some("application/json")),
+            BulkBodyFormat::ElasticBulk
+        );
+        assert_eq!(BulkBodyFormat::from_content_type(None), BulkBodyFormat::ElasticBulk);
+    }
+
+    #[test]
+    fn test_ndjson_to_bulk_ndjson() {
+        let body = Bytes::from("{\"a\":1}\n\n{\"a\":2}\n");
+        let bulk = ndjson_to_bulk_ndjson(&body, "my-index").unwrap();
+        let text = std::str::from_utf8(&bulk).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "{\"index\":{\"_index\":\"my-index\"}}");
+        assert_eq!(lines[1], "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_csv_to_bulk_ndjson_with_type_inference() {
+        let body = Bytes::from("name,age,active\nalice,30,true\nbob,,false\n");
+        let bulk = csv_to_bulk_ndjson(&body, "people", true).unwrap();
+        let text = std::str::from_utf8(&bulk).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let first_doc: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(first_doc["age"], serde_json::json!(30));
+        assert_eq!(first_doc["active"], serde_json::json!(true));
+        let second_doc: serde_json::Value = serde_json::from_str(lines[3]).unwrap();
+        assert!(second_doc.get("age").is_none());
+    }
+
+    #[test]
+    fn test_infer_csv_value_preserves_non_round_tripping_strings() {
+        // Leading-zero strings (ZIP codes) must stay strings: parsing and
+        // reformatting them as an integer would drop the leading zero.
+        assert_eq!(infer_csv_value("01234"), serde_json::json!("01234"));
+        // More digits than `i64` can hold without losing precision.
+        assert_eq!(
+            infer_csv_value("123456789012345678901234567890"),
+            serde_json::json!("123456789012345678901234567890")
+        );
+        // Non-finite floats have no JSON representation and would
+        // otherwise serialize as `null`, silently dropping the field.
+        assert_eq!(infer_csv_value("inf"), serde_json::json!("inf"));
+        assert_eq!(infer_csv_value("nan"), serde_json::json!("nan"));
+        assert_eq!(infer_csv_value("30"), serde_json::json!(30));
+    }
+
+    #[test]
+    fn test_csv_to_bulk_ndjson_without_type_inference() {
+        let body = Bytes::from("age\n30\n");
+        let bulk = csv_to_bulk_ndjson(&body, "people", false).unwrap();
+        let text = std::str::from_utf8(&bulk).unwrap();
+        let doc: serde_json::Value = serde_json::from_str(text.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(doc["age"], serde_json::json!("30"));
+    }
+}
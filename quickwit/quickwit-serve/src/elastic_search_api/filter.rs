@@ -23,6 +23,7 @@ use serde::de::DeserializeOwned;
 use warp::reject::LengthRequired;
 use warp::{Filter, Rejection};
 
+use super::bulk_body::{normalize_to_bulk_ndjson, BulkBodyFormat, BulkFormatQueryParams};
 use super::model::MultiSearchQueryParams;
 use crate::elastic_search_api::model::{ElasticIngestOptions, SearchBody, SearchQueryParams};
 
@@ -70,8 +71,49 @@ pub(crate) fn elastic_bulk_filter(
         .and(warp::body::content_length_limit(
             CONTENT_LENGTH_LIMIT.get_bytes(),
         ))
+        .and(warp::header::optional::<String>("content-type"))
         .and(warp::body::bytes())
-        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(serde_qs::warp::query::<ElasticIngestOptions>(
+            serde_qs::Config::default(),
+        ))
+        .and(serde_qs::warp::query::<BulkFormatQueryParams>(
+            serde_qs::Config::default(),
+        ))
+        .and_then(
+            |content_type: Option<String>,
+             body: Bytes,
+             options: ElasticIngestOptions,
+             format_params: BulkFormatQueryParams| async move {
+                let body = normalize_indexless_bulk_body(content_type, body, &format_params)?;
+                Ok::<_, Rejection>((body, options))
+            },
+        )
+        .untuple_one()
+}
+
+/// Normalizes the body of the index-less `/_elastic/_bulk` endpoint.
+///
+/// The ES bulk action/meta format is passed through unchanged. CSV and
+/// plain NDJSON documents have no per-document index, so they require the
+/// target index to be given as the `index` query param.
+fn normalize_indexless_bulk_body(
+    content_type: Option<String>,
+    body: Bytes,
+    format_params: &BulkFormatQueryParams,
+) -> Result<Bytes, Rejection> {
+    let format = BulkBodyFormat::from_content_type(content_type.as_deref());
+    if format == BulkBodyFormat::ElasticBulk {
+        return Ok(body);
+    }
+    let index = format_params.index.as_deref().ok_or_else(|| {
+        warp::reject::custom(crate::rest::InvalidArgument(
+            "the `index` query param is required when posting CSV or NDJSON documents to \
+             `/_elastic/_bulk`"
+                .to_string(),
+        ))
+    })?;
+    normalize_to_bulk_ndjson(format, &body, index, format_params.infer_types())
+        .map_err(|error| warp::reject::custom(crate::rest::InvalidArgument(error.to_string())))
 }
 
 /// Like the warp json filter, but accepts an empty body and interprets it as `T::default`.
@@ -140,10 +182,94 @@ pub(crate) fn elastic_index_bulk_filter(
         .and(warp::body::content_length_limit(
             CONTENT_LENGTH_LIMIT.get_bytes(),
         ))
+        .and(warp::header::optional::<String>("content-type"))
         .and(warp::body::bytes())
         .and(serde_qs::warp::query::<ElasticIngestOptions>(
             serde_qs::Config::default(),
         ))
+        .and(serde_qs::warp::query::<BulkFormatQueryParams>(
+            serde_qs::Config::default(),
+        ))
+        .and_then(
+            |index: String,
+             content_type: Option<String>,
+             body: Bytes,
+             options: ElasticIngestOptions,
+             format_params: BulkFormatQueryParams| async move {
+                let format = BulkBodyFormat::from_content_type(content_type.as_deref());
+                let body = normalize_to_bulk_ndjson(format, &body, &index, format_params.infer_types())
+                    .map_err(|error| {
+                        warp::reject::custom(crate::rest::InvalidArgument(error.to_string()))
+                    })?;
+                Ok::<_, Rejection>((index, body, options))
+            },
+        )
+        .untuple_one()
+}
+
+/// Like [`elastic_index_bulk_filter`], but without the Elasticsearch
+/// action/meta wrapping: a plain document stream (NDJSON or CSV) targeting
+/// a single index. `Content-Type` still selects the format; defaults to
+/// NDJSON when absent, since there is no bulk-action format for this
+/// endpoint to fall back to.
+#[utoipa::path(
+    post,
+    tag = "Ingest",
+    path = "/{index}/_ingest",
+    request_body(content = String, description = "NDJSON or CSV documents, limited to 10MB", content_type = "application/x-ndjson"),
+    responses(
+        (status = 200, description = "Successfully ingested documents.", body = IngestResponse)
+    ),
+    params(
+        ("refresh" = Option<ElasticRefresh>, Query, description = "Force or wait for commit at the end of the indexing operation."),
+        ("infer_types" = Option<bool>, Query, description = "Infer numeric/boolean types for CSV cells. Defaults to true."),
+    )
+)]
+pub(crate) fn elastic_index_ingest_filter(
+) -> impl Filter<Extract = (String, Bytes, ElasticIngestOptions), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_ingest")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(
+            CONTENT_LENGTH_LIMIT.get_bytes(),
+        ))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::bytes())
+        .and(serde_qs::warp::query::<ElasticIngestOptions>(
+            serde_qs::Config::default(),
+        ))
+        .and(serde_qs::warp::query::<BulkFormatQueryParams>(
+            serde_qs::Config::default(),
+        ))
+        .and_then(
+            |index: String,
+             content_type: Option<String>,
+             body: Bytes,
+             options: ElasticIngestOptions,
+             format_params: BulkFormatQueryParams| async move {
+                let format = match BulkBodyFormat::from_content_type(content_type.as_deref()) {
+                    BulkBodyFormat::ElasticBulk => BulkBodyFormat::NdJson,
+                    format => format,
+                };
+                let body = normalize_to_bulk_ndjson(format, &body, &index, format_params.infer_types())
+                    .map_err(|error| {
+                        warp::reject::custom(crate::rest::InvalidArgument(error.to_string()))
+                    })?;
+                Ok::<_, Rejection>((index, body, options))
+            },
+        )
+        .untuple_one()
+}
+
+/// Every filter that feeds the bulk ingest path for a single index,
+/// combined into one: `/{index}/_bulk` (Elasticsearch action/meta format,
+/// or CSV/NDJSON via `Content-Type`) and `/{index}/_ingest` (CSV/NDJSON
+/// only, no per-document action line). Both normalize to the same
+/// `(String, Bytes, ElasticIngestOptions)` shape, so the router attaches
+/// this combinator — not `elastic_index_bulk_filter` alone — to the bulk
+/// ingest handler to make `/{index}/_ingest` reachable.
+pub(crate) fn elastic_index_bulk_and_ingest_filter(
+) -> impl Filter<Extract = (String, Bytes, ElasticIngestOptions), Error = Rejection> + Clone {
+    elastic_index_bulk_filter().or(elastic_index_ingest_filter()).unify()
 }
 
 #[utoipa::path(post, tag = "Search", path = "/_msearch")]
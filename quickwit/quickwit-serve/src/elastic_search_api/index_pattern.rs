@@ -0,0 +1,180 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Resolves the comma-separated/glob index patterns a multi-index
+//! `/{index}/_search` would accept (e.g. `"logs-a,logs-b"`,
+//! `"logs-2023-*"`, `"*"`) against the metastore's actual index set.
+//!
+//! This is the resolution half of multi-index search: turning a pattern
+//! list into the concrete index ids to query. It does not fan a query out
+//! to those indices, merge their hits, or sum aggregation buckets — that
+//! requires the search execution layer (the code that actually runs a
+//! query against a resolved index and knows the hit/aggregation response
+//! shape), which this crate doesn't have visibility into here. Wiring this
+//! resolver in front of that layer is the remaining step to serve
+//! multi-index search end-to-end.
+
+/// Splits a `/{index}/_search` path segment into the list of index
+/// patterns it names, e.g. `"logs-a,logs-b"` into `["logs-a", "logs-b"]`
+/// and `"logs-2023-*"` into `["logs-2023-*"]`.
+pub(crate) fn parse_index_patterns(comma_separated_indexes: &str) -> Vec<String> {
+    comma_separated_indexes
+        .split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub(crate) enum ResolveIndexPatternsError {
+    #[error("no index matches any of the given patterns: {patterns:?}")]
+    NoMatchingIndex { patterns: Vec<String> },
+}
+
+/// Resolves `patterns` against `available_index_ids`, expanding glob
+/// patterns (`*` only, e.g. `logs-2023-*`, `*`) and de-duplicating indices
+/// matched by more than one pattern. Matches are returned in
+/// `available_index_ids`'s order, not the patterns' order, since a single
+/// glob can match indices that came from different patterns.
+///
+/// Mirrors Elasticsearch's `allow_no_indices`: when `false`, resolving to an empty set is an error rather than a silently empty
+/// search.
+pub(crate) fn resolve_index_patterns(
+    patterns: &[String],
+    available_index_ids: &[String],
+    allow_no_indices: bool,
+) -> Result<Vec<String>, ResolveIndexPatternsError> {
+    let matched: Vec<String> = available_index_ids
+        .iter()
+        .filter(|index_id| patterns.iter().any(|pattern| matches_pattern(pattern, index_id)))
+        .cloned()
+        .collect();
+    if matched.is_empty() && !allow_no_indices {
+        return Err(ResolveIndexPatternsError::NoMatchingIndex {
+            patterns: patterns.to_vec(),
+        });
+    }
+    Ok(matched)
+}
+
+/// Matches `candidate` against `pattern`, where `*` in `pattern` matches
+/// any (possibly empty) run of characters. Exact match when `pattern` has
+/// no `*`.
+fn matches_pattern(pattern: &str, candidate: &str) -> bool {
+    if !pattern.contains('*') {
+        return pattern == candidate;
+    }
+    let segments: Vec<&str> = pattern.split('*').collect();
+    let mut rest = candidate;
+
+    if let Some(first) = segments.first() {
+        if !first.is_empty() {
+            let Some(stripped) = rest.strip_prefix(first) else {
+                return false;
+            };
+            rest = stripped;
+        }
+    }
+    let last_index = segments.len() - 1;
+    for (i, segment) in segments.iter().enumerate().skip(1) {
+        if i == last_index {
+            if segment.is_empty() {
+                return true;
+            }
+            return rest.ends_with(segment);
+        }
+        if segment.is_empty() {
+            continue;
+        }
+        let Some(found) = rest.find(segment) else {
+            return false;
+        };
+        rest = &rest[found + segment.len()..];
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index_patterns() {
+        assert_eq!(
+            parse_index_patterns("logs-a, logs-b"),
+            vec!["logs-a".to_string(), "logs-b".to_string()]
+        );
+        assert_eq!(parse_index_patterns(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_matches_pattern_exact() {
+        assert!(matches_pattern("logs-a", "logs-a"));
+        assert!(!matches_pattern("logs-a", "logs-b"));
+    }
+
+    #[test]
+    fn test_matches_pattern_glob() {
+        assert!(matches_pattern("logs-2023-*", "logs-2023-01"));
+        assert!(!matches_pattern("logs-2023-*", "logs-2024-01"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("*-01", "logs-2023-01"));
+        assert!(matches_pattern("logs-*-01", "logs-2023-01"));
+        assert!(!matches_pattern("logs-*-01", "logs-2023-02"));
+    }
+
+    #[test]
+    fn test_resolve_index_patterns() {
+        let available = vec![
+            "logs-2023-01".to_string(),
+            "logs-2023-02".to_string(),
+            "metrics-2023-01".to_string(),
+        ];
+        let resolved =
+            resolve_index_patterns(&["logs-2023-*".to_string()], &available, false).unwrap();
+        assert_eq!(resolved, vec!["logs-2023-01".to_string(), "logs-2023-02".to_string()]);
+
+        let resolved = resolve_index_patterns(
+            &["logs-2023-01".to_string(), "metrics-2023-01".to_string()],
+            &available,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            vec!["logs-2023-01".to_string(), "metrics-2023-01".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_resolve_index_patterns_no_match() {
+        let available = vec!["logs-2023-01".to_string()];
+        let error =
+            resolve_index_patterns(&["no-such-index".to_string()], &available, false).unwrap_err();
+        assert_eq!(
+            error,
+            ResolveIndexPatternsError::NoMatchingIndex {
+                patterns: vec!["no-such-index".to_string()]
+            }
+        );
+        assert!(resolve_index_patterns(&["no-such-index".to_string()], &available, true)
+            .unwrap()
+            .is_empty());
+    }
+}
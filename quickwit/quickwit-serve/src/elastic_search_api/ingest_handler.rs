@@ -0,0 +1,86 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! The handler the `_bulk`/`_ingest` routes attach after
+//! [`super::filter::elastic_index_bulk_and_ingest_filter`], putting the
+//! [`UpdateFileStore`] staging step in front of the actual ingest call the
+//! way [`crate::ingest_staging`]'s module doc describes.
+//!
+//! The route tree binds `store` and `ingest` once at server startup (one
+//! [`UpdateFileStore`] per node, one ingest call per configured ingest
+//! router client) and passes this function to `.and_then(...)` so every
+//! request closes over them.
+
+use std::future::Future;
+
+use bytes::Bytes;
+use quickwit_proto::indexing::IndexingError;
+use quickwit_proto::IndexUid;
+
+use crate::ingest_staging::{replay_orphaned, stage_then_ingest, UpdateFileStore};
+
+/// Source id staged bulk/ingest payloads are recorded under: the `_bulk`
+/// and `_ingest` endpoints don't take a source in the request, since every
+/// index has exactly one built-in ingest-API source.
+pub(crate) const INGEST_API_SOURCE_ID: &str = "_ingest-api-source";
+
+/// Stages `bytes` durably, then hands it to `ingest`, clearing the staged
+/// copy once `ingest` confirms the commit. This is the function the
+/// `_bulk`/`_ingest` route tree attaches after the bulk/ingest filters so
+/// every accepted request is staged before the pipeline ever sees it.
+pub(crate) async fn handle_staged_ingest<F, Fut>(
+    store: &dyn UpdateFileStore,
+    index_uid: IndexUid,
+    received_at: i64,
+    bytes: Bytes,
+    ingest: F,
+) -> Result<(), IndexingError>
+where
+    F: FnOnce(Bytes) -> Fut,
+    Fut: Future<Output = Result<(), IndexingError>>,
+{
+    stage_then_ingest(
+        store,
+        index_uid,
+        INGEST_API_SOURCE_ID.to_string(),
+        received_at,
+        bytes,
+        ingest,
+    )
+    .await
+}
+
+/// Replays every payload left behind by an unclean shutdown. Called once
+/// at server startup, before the node starts accepting `_bulk`/`_ingest`
+/// traffic, so a crash between staging a payload and confirming its commit
+/// doesn't silently drop it.
+pub(crate) async fn replay_staged_on_startup<F, Fut>(
+    store: &dyn UpdateFileStore,
+    ingest: F,
+) -> Result<usize, IndexingError>
+where
+    F: FnMut(crate::ingest_staging::StagedPayload) -> Fut,
+    Fut: Future<Output = Result<(), IndexingError>>,
+{
+    let replayed = replay_orphaned(store, ingest).await?;
+    if replayed > 0 {
+        tracing::info!(replayed, "replayed orphaned staged bulk/ingest payloads");
+    }
+    Ok(replayed)
+}
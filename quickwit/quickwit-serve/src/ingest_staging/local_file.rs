@@ -0,0 +1,142 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use quickwit_proto::indexing::IndexingError;
+use quickwit_proto::IndexUid;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+
+use super::{StagedPayload, StagedPayloadId, UpdateFileStore};
+
+/// Sidecar metadata written next to each staged payload's bytes, so a
+/// staged file can be fully replayed without any other source of truth.
+#[derive(Debug, Serialize, Deserialize)]
+struct StagedPayloadMeta {
+    index_uid: String,
+    source_id: String,
+    received_at: i64,
+}
+
+/// [`UpdateFileStore`] backed by a local directory. Each staged payload is
+/// two files named after its UUID: `<id>.payload` (the raw bytes) and
+/// `<id>.meta.json` (the sidecar above). `commit` removes both; whatever
+/// is still on disk after an unclean shutdown is, by construction, exactly
+/// the set of payloads that never got confirmed.
+pub struct LocalFileUpdateStore {
+    base_dir: PathBuf,
+}
+
+impl LocalFileUpdateStore {
+    /// Opens (creating if necessary) a staging directory at `base_dir`.
+    pub async fn open(base_dir: PathBuf) -> Result<Self, IndexingError> {
+        fs::create_dir_all(&base_dir).await.map_err(IndexingError::Io)?;
+        Ok(LocalFileUpdateStore { base_dir })
+    }
+
+    fn payload_path(&self, id: StagedPayloadId) -> PathBuf {
+        self.base_dir.join(format!("{id}.payload"))
+    }
+
+    fn meta_path(&self, id: StagedPayloadId) -> PathBuf {
+        self.base_dir.join(format!("{id}.meta.json"))
+    }
+}
+
+#[async_trait::async_trait]
+impl UpdateFileStore for LocalFileUpdateStore {
+    async fn stage(&self, payload: &StagedPayload) -> Result<(), IndexingError> {
+        let meta = StagedPayloadMeta {
+            index_uid: payload.index_uid.to_string(),
+            source_id: payload.source_id.clone(),
+            received_at: payload.received_at,
+        };
+        let meta_json = serde_json::to_vec(&meta).map_err(|error| {
+            IndexingError::Internal(format!("failed to serialize staged payload metadata: {error}"))
+        })?;
+
+        // Write the bytes before the sidecar: a crash between the two
+        // writes leaves an orphaned `.payload` with no `.meta.json`, which
+        // `list_orphaned` below simply ignores, rather than a `.meta.json`
+        // pointing at bytes that were never durably written. Each file is
+        // `fsync`'d before the next one starts: a plain `fs::write` only
+        // guarantees the data reached the page cache, not disk, so without
+        // this a crash right after `stage` returns could still lose the
+        // payload it just promised was durable.
+        write_and_sync(&self.payload_path(payload.id), &payload.bytes).await?;
+        write_and_sync(&self.meta_path(payload.id), &meta_json).await?;
+        Ok(())
+    }
+
+    async fn commit(&self, id: StagedPayloadId) -> Result<(), IndexingError> {
+        // Best-effort: a file already gone (e.g. a concurrent replay
+        // committed it first) is not an error for the caller confirming
+        // its own commit.
+        let _ = fs::remove_file(self.meta_path(id)).await;
+        let _ = fs::remove_file(self.payload_path(id)).await;
+        Ok(())
+    }
+
+    async fn list_orphaned(&self) -> Result<Vec<StagedPayload>, IndexingError> {
+        let mut entries = fs::read_dir(&self.base_dir).await.map_err(IndexingError::Io)?;
+        let mut staged = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(IndexingError::Io)? {
+            let path = entry.path();
+            let Some(id) = meta_id_from_path(&path) else {
+                continue;
+            };
+            let payload_path = self.payload_path(id);
+            if !payload_path.exists() {
+                continue;
+            }
+            let meta_bytes = fs::read(&path).await.map_err(IndexingError::Io)?;
+            let meta: StagedPayloadMeta = serde_json::from_slice(&meta_bytes).map_err(|error| {
+                IndexingError::Internal(format!("corrupt staged payload metadata {id}: {error}"))
+            })?;
+            let bytes = fs::read(&payload_path).await.map_err(IndexingError::Io)?;
+            staged.push(StagedPayload {
+                id,
+                index_uid: IndexUid::from(meta.index_uid),
+                source_id: meta.source_id,
+                received_at: meta.received_at,
+                bytes: Bytes::from(bytes),
+            });
+        }
+        Ok(staged)
+    }
+}
+
+/// Writes `bytes` to `path` and `fsync`s the file before returning, so the
+/// write survives a crash immediately afterwards rather than only living in
+/// the page cache.
+async fn write_and_sync(path: &Path, bytes: &[u8]) -> Result<(), IndexingError> {
+    let mut file = File::create(path).await.map_err(IndexingError::Io)?;
+    file.write_all(bytes).await.map_err(IndexingError::Io)?;
+    file.sync_all().await.map_err(IndexingError::Io)?;
+    Ok(())
+}
+
+fn meta_id_from_path(path: &Path) -> Option<StagedPayloadId> {
+    let name = path.file_name()?.to_str()?;
+    let id_str = name.strip_suffix(".meta.json")?;
+    StagedPayloadId::parse_str(id_str).ok()
+}
@@ -0,0 +1,144 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Durable staging for accepted-but-not-yet-indexed bulk payloads.
+//!
+//! `_bulk` (and `_ingest`) currently accept a payload, hand it to the
+//! indexing pipeline, and forget it. If the pipeline fails to spawn
+//! ([`quickwit_proto::indexing::IndexingError::SpawnPipelinesError`]) or is
+//! momentarily [`quickwit_proto::indexing::IndexingError::Unavailable`]
+//! after the request was accepted, those documents are gone. This module
+//! writes every accepted payload to an [`UpdateFileStore`] before handing
+//! it to the pipeline, and only deletes it once the corresponding commit
+//! is confirmed, so a spawn failure leaves something to [`replay_orphaned`]
+//! instead of silently dropping documents.
+//!
+//! [`stage_then_ingest`] must wrap the call into the ingest router, not the
+//! `_bulk`/`_ingest` warp filters: a [`StagedPayload`] is keyed by
+//! `source_id`, which the filters never see (they only parse `index` and
+//! the request body; the source a document lands on is resolved later, by
+//! the ingest router). The bulk/ingest request handler is expected to call
+//! [`stage_then_ingest`] once it has that `source_id`, and the server
+//! startup path is expected to call [`replay_orphaned`] once against every
+//! configured [`UpdateFileStore`] before accepting traffic.
+
+mod local_file;
+
+use std::future::Future;
+
+use bytes::Bytes;
+use quickwit_proto::indexing::IndexingError;
+use quickwit_proto::IndexUid;
+use uuid::Uuid;
+
+pub use local_file::LocalFileUpdateStore;
+
+/// Uniquely identifies one staged payload.
+pub type StagedPayloadId = Uuid;
+
+/// An accepted bulk payload, staged before the indexing pipeline is asked
+/// to process it.
+#[derive(Debug, Clone)]
+pub struct StagedPayload {
+    pub id: StagedPayloadId,
+    pub index_uid: IndexUid,
+    pub source_id: String,
+    pub received_at: i64,
+    pub bytes: Bytes,
+}
+
+/// Storage backend for staged bulk payloads.
+///
+/// Implementations must make `stage` durable before returning (e.g. `fsync`
+/// on local disk, or a confirmed object store `put`) since a crash between
+/// accepting the request and staging it would reintroduce the data-loss
+/// window this module exists to close.
+#[async_trait::async_trait]
+pub trait UpdateFileStore: Send + Sync + 'static {
+    /// Durably persists `payload`, keyed by `payload.id`.
+    async fn stage(&self, payload: &StagedPayload) -> Result<(), IndexingError>;
+
+    /// Deletes a staged payload once its commit has been confirmed.
+    async fn commit(&self, id: StagedPayloadId) -> Result<(), IndexingError>;
+
+    /// Lists every payload still on disk, i.e. every payload whose pipeline
+    /// never confirmed a commit for it. Called once at startup and
+    /// whenever an operator wants to force a replay.
+    async fn list_orphaned(&self) -> Result<Vec<StagedPayload>, IndexingError>;
+}
+
+/// Stages `bytes` durably, invokes `ingest` with it, and only deletes the
+/// staged copy once `ingest` confirms the commit. On
+/// [`IndexingError::SpawnPipelinesError`] or [`IndexingError::Unavailable`],
+/// the staged payload is left in place for [`replay_orphaned`] to pick up
+/// later; any other error also leaves it staged, since the safe default
+/// for an ingest path is to keep the data until we are sure it landed.
+pub async fn stage_then_ingest<F, Fut>(
+    store: &dyn UpdateFileStore,
+    index_uid: IndexUid,
+    source_id: String,
+    received_at: i64,
+    bytes: Bytes,
+    ingest: F,
+) -> Result<(), IndexingError>
+where
+    F: FnOnce(Bytes) -> Fut,
+    Fut: Future<Output = Result<(), IndexingError>>,
+{
+    let payload = StagedPayload {
+        id: Uuid::new_v4(),
+        index_uid,
+        source_id,
+        received_at,
+        bytes: bytes.clone(),
+    };
+    store.stage(&payload).await?;
+
+    match ingest(bytes).await {
+        Ok(()) => {
+            store.commit(payload.id).await?;
+            Ok(())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Re-submits every payload [`UpdateFileStore::list_orphaned`] still holds,
+/// deleting each one from the store as soon as its re-submission succeeds.
+/// Stops at the first failed re-submission and returns its error: that
+/// payload and any not yet tried stay staged. Run at startup and may also be invoked on demand to retry payloads left
+/// behind by a since-resolved outage.
+pub async fn replay_orphaned<F, Fut>(
+    store: &dyn UpdateFileStore,
+    mut ingest: F,
+) -> Result<usize, IndexingError>
+where
+    F: FnMut(StagedPayload) -> Fut,
+    Fut: Future<Output = Result<(), IndexingError>>,
+{
+    let orphaned = store.list_orphaned().await?;
+    let mut replayed = 0;
+    for payload in orphaned {
+        let id = payload.id;
+        ingest(payload).await?;
+        store.commit(id).await?;
+        replayed += 1;
+    }
+    Ok(replayed)
+}
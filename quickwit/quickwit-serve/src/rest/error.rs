@@ -0,0 +1,129 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! The JSON error body shared by every REST handler.
+//!
+//! `ServiceError` already carries an HTTP-ish `ServiceErrorCode` for the
+//! status line; this module turns the same error into the response *body*,
+//! adding the fields clients need to branch on programmatically without
+//! string-matching `message`: a stable `code`, a broad `type`, and a docs
+//! `link` built deterministically from the code.
+//!
+//! Handlers that want this body reject with
+//! `warp::reject::custom(ServiceErrorRejection(Box::new(error)))`; the
+//! route tree's `.recover(recover_service_error)` turns that rejection into
+//! the JSON reply.
+
+use quickwit_proto::{ErrorType, ServiceError, ServiceErrorCode};
+use serde::Serialize;
+use warp::http::StatusCode;
+use warp::{Rejection, Reply};
+
+/// Base URL the per-error documentation page is appended to. Kept as a
+/// constant so every handler links to the same place even as new error
+/// codes are added.
+const ERROR_DOCS_BASE_URL: &str = "https://quickwit.io/docs/reference/errors";
+
+/// Response body returned by every REST endpoint on error.
+#[derive(Debug, Serialize)]
+pub(crate) struct ApiError {
+    pub message: String,
+    pub code: &'static str,
+    pub r#type: ErrorType,
+    pub link: String,
+}
+
+impl ApiError {
+    /// Builds the response body for `error`, deriving `link` from
+    /// [`ServiceError::error_code`] so the two never drift apart.
+    pub fn new(error: &dyn ServiceError) -> Self {
+        let code = error.error_code();
+        ApiError {
+            message: error.to_string(),
+            code,
+            r#type: error.error_type(),
+            link: docs_link(code),
+        }
+    }
+}
+
+/// Deterministically builds the docs link for a stable error `code`, e.g.
+/// `missing_pipeline` -> `https://quickwit.io/docs/reference/errors#missing_pipeline`.
+fn docs_link(code: &str) -> String {
+    format!("{ERROR_DOCS_BASE_URL}#{code}")
+}
+
+fn http_status(code: ServiceErrorCode) -> StatusCode {
+    match code {
+        ServiceErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+        ServiceErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ServiceErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        ServiceErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// Lets any [`ServiceError`] be rejected from a warp filter and rendered
+/// into the shared `ApiError` JSON body by [`recover_service_error`].
+pub(crate) struct ServiceErrorRejection(pub Box<dyn ServiceError + Send + Sync>);
+
+impl std::fmt::Debug for ServiceErrorRejection {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "ServiceErrorRejection({})", self.0)
+    }
+}
+
+impl warp::reject::Reject for ServiceErrorRejection {}
+
+/// Rejection handler installed on every REST route (via `.recover(...)`)
+/// that renders a [`ServiceErrorRejection`] as the shared
+/// `{message, code, type, link}` JSON body, with the status line taken
+/// from [`ServiceError::status_code`]. Rejections it doesn't recognize are
+/// passed through unchanged for an outer `.recover(...)` to handle.
+pub(crate) async fn recover_service_error(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    let Some(service_error_rejection) = rejection.find::<ServiceErrorRejection>() else {
+        return Err(rejection);
+    };
+    let error = service_error_rejection.0.as_ref();
+    let body = ApiError::new(error);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&body),
+        http_status(error.status_code()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::IndexingError;
+
+    use super::*;
+
+    #[test]
+    fn test_api_error_link_matches_code() {
+        let error = IndexingError::MissingPipeline {
+            index_id: "my-index".to_string(),
+            source_id: "my-source".to_string(),
+        };
+        let api_error = ApiError::new(&error);
+        assert_eq!(api_error.code, "missing_pipeline");
+        assert_eq!(
+            api_error.link,
+            "https://quickwit.io/docs/reference/errors#missing_pipeline"
+        );
+    }
+}